@@ -1,49 +1,142 @@
+use std::collections::TryReserveError;
 use std::io::{Read, Write};
 
 // change: do NOT derive PartialEq (it does not make sense)
 #[derive(Debug, Clone)]
-pub struct Buffer {
+pub struct Buffer<T: Copy + Default = u8> {
     /// backing buffer
-    buf: Vec<u8>,
+    buf: Vec<T>,
     /// pointer to start of data
     start: usize,
     /// pointer to end of data
     end: usize,
     // change: no capacity! the vec has one already
+    /// if true, `start`/`end` are interpreted modulo `capacity()` and the
+    /// buffer wraps around the backing allocation instead of shifting
+    circular: bool,
+    /// if true, the `Write` impl reserves space (reclaiming consumed bytes,
+    /// then amortized-doubling) instead of truncating to the current capacity
+    grow_on_write: bool,
 }
 
-// TODO: generic?
-impl Buffer {
+impl<T: Copy + Default> Buffer<T> {
     /// create buffer with given capacity
-    pub fn with_capacity(capacity: usize) -> Buffer {
-        // change: use vec macro to construct zeroed vec
-        let buf = vec![0; capacity];
-        Buffer {
+    pub fn with_capacity(capacity: usize) -> Buffer<T> {
+        // change: thin wrapper over the fallible variant that aborts on failure
+        Buffer::try_with_capacity(capacity).expect("failed to allocate buffer")
+    }
+
+    /// create buffer with given capacity, returning an error instead of
+    /// aborting the process on allocation failure (RFC 2116)
+    pub fn try_with_capacity(capacity: usize) -> Result<Buffer<T>, TryReserveError> {
+        // change: reserve fallibly, then fill the reservation with defaults
+        let mut buf = Vec::new();
+        buf.try_reserve_exact(capacity)?;
+        buf.resize(capacity, T::default());
+        Ok(Buffer {
             buf,
             start: 0,
             end: 0,
-        }
+            circular: false,
+            grow_on_write: false,
+        })
+    }
+
+    /// create a circular (wraparound) buffer with the given capacity
+    ///
+    /// in this mode reclaiming space never relocates data: `start` and `end`
+    /// chase each other around the fixed allocation, so there is no O(n)
+    /// `shift()` on the hot path. use `data_segments`/`space_segments` to
+    /// access the (possibly split) live and free runs.
+    pub fn with_capacity_circular(capacity: usize) -> Buffer<T> {
+        let mut buf = Buffer::with_capacity(capacity);
+        buf.circular = true;
+        buf
     }
 
     /// create buffer by copying slice
-    pub fn from_slice(data: &[u8]) -> Buffer {
+    pub fn from_slice(data: &[T]) -> Buffer<T> {
         Buffer {
             buf: data.to_owned(),
             start: 0,
             end: data.len(),
+            circular: false,
+            grow_on_write: false,
         }
     }
 
+    /// create an auto-growing buffer with the given initial capacity
+    ///
+    /// writes through the `Write` impl will never truncate: when the incoming
+    /// data does not fit, the buffer first reclaims consumed space and then
+    /// amortized-doubles its backing allocation (see `reserve`).
+    pub fn with_growth(capacity: usize) -> Buffer<T> {
+        let mut buf = Buffer::with_capacity(capacity);
+        buf.grow_on_write = true;
+        buf
+    }
+
     /// resize buffer to a larger size
     pub fn grow(&mut self, new_size: usize) -> bool {
-        if new_size <= self.buf.capacity() {
-            false
+        // change: thin wrapper over the fallible variant that aborts on failure
+        self.try_grow(new_size).expect("failed to grow buffer")
+    }
+
+    /// resize buffer to a larger size, returning an error instead of aborting
+    /// the process on allocation failure (RFC 2116)
+    pub fn try_grow(&mut self, new_size: usize) -> Result<bool, TryReserveError> {
+        if new_size <= self.capacity() {
+            Ok(false)
         } else {
-            self.buf.resize(new_size, 0);
-            true
+            if self.circular {
+                // coalesce any wrapped data to the front first: resize() only
+                // appends at the physical end, which would otherwise split the
+                // logical run and lose the wrapped tail
+                self.shift();
+            }
+            let additional = new_size - self.buf.len();
+            self.buf.try_reserve_exact(additional)?;
+            self.buf.resize(new_size, T::default());
+            Ok(true)
         }
     }
 
+    /// ensure at least `additional` elements of space are available to write,
+    /// growing the backing allocation if necessary
+    pub fn reserve(&mut self, additional: usize) {
+        // change: thin wrapper over the fallible variant that aborts on failure
+        self.try_reserve(additional)
+            .expect("failed to reserve buffer capacity");
+    }
+
+    /// ensure at least `additional` elements of space are available to write,
+    /// returning an error instead of aborting on allocation failure
+    ///
+    /// like `BytesMut`, this first reclaims consumed space with `shift()` and
+    /// only then amortized-doubles the backing allocation, giving `O(1)`
+    /// amortized appends.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), TryReserveError> {
+        if self.available_space() >= additional {
+            return Ok(());
+        }
+        // reclaim already-consumed space before allocating more
+        self.shift();
+        if self.available_space() >= additional {
+            return Ok(());
+        }
+        // after shift() the live data sits at [0, end); grow to fit it plus the
+        // requested amount, doubling so repeated reserves stay amortized O(1)
+        let needed = self.end.saturating_add(additional);
+        let mut new_cap = self.capacity().max(1);
+        while new_cap < needed {
+            // saturate the doubling so a pathologically large reserve surfaces
+            // as a TryReserveError from try_grow instead of overflow-panicking
+            new_cap = new_cap.checked_mul(2).unwrap_or(needed).max(needed);
+        }
+        self.try_grow(new_cap)?;
+        Ok(())
+    }
+
     /// return data currently available to consume
     pub fn available_data(&self) -> usize {
         self.end - self.start
@@ -51,12 +144,21 @@ impl Buffer {
 
     /// return available space for new data
     pub fn available_space(&self) -> usize {
-        self.buf.capacity() - self.end
+        if self.circular {
+            // in circular mode the free region is whatever is not live data,
+            // split or not
+            self.capacity() - self.available_data()
+        } else {
+            self.capacity() - self.end
+        }
     }
 
     /// return capacity of backing buffer
     pub fn capacity(&self) -> usize {
-        self.buf.capacity()
+        // the usable region is the initialized length, not the raw allocation:
+        // the allocator may hand back more than we asked for, but only `len`
+        // elements are initialized and indexable, so every accessor agrees on this
+        self.buf.len()
     }
 
     /// return if buffer is empty (no data to read)
@@ -71,6 +173,12 @@ impl Buffer {
             panic!("attempted to consume more data than available");
         }
         self.start += count;
+        if self.circular && self.start >= self.capacity() {
+            // keep both heads bounded within one lap of the ring
+            let cap = self.capacity();
+            self.start -= cap;
+            self.end -= cap;
+        }
         // change: does not shift()
         count
     }
@@ -103,18 +211,90 @@ impl Buffer {
         self.end = 0;
     }
 
-    /// returns slice with data available to read
-    pub fn data(&self) -> &[u8] {
+    /// returns slice with all data available to read as one contiguous run
+    ///
+    /// takes `&mut self` because in circular mode a wrapped buffer is lazily
+    /// rotated (via `shift()`) so the returned slice always covers the full
+    /// `available_data()`. use `data_segments()` to read the (possibly split)
+    /// runs without relocating.
+    pub fn data(&mut self) -> &[T] {
+        if self.circular {
+            self.shift();
+        }
         &self.buf[self.start..self.end]
     }
 
     /// returns slice with space available to write
-    pub fn space(&mut self) -> &mut [u8] {
+    ///
+    /// in circular mode the free region may be split; requesting a single
+    /// slice lazily rotates the live data to the front (via `shift()`) so the
+    /// free space becomes one contiguous run. use `space_segments()` to write
+    /// into both runs without relocating.
+    pub fn space(&mut self) -> &mut [T] {
+        if self.circular {
+            self.shift();
+        }
         &mut self.buf[self.end..]
     }
 
+    /// return the (up to) two contiguous runs of live data
+    ///
+    /// the second run is empty unless the data wraps around the end of the
+    /// backing allocation; it is always empty in non-circular mode.
+    pub fn data_segments(&self) -> (&[T], &[T]) {
+        let cap = self.capacity();
+        if !self.circular || cap == 0 {
+            return (&self.buf[self.start..self.end], &[]);
+        }
+        let len = self.available_data();
+        let s = self.start % cap;
+        if s + len <= cap {
+            (&self.buf[s..s + len], &[])
+        } else {
+            let first = cap - s;
+            (&self.buf[s..cap], &self.buf[..len - first])
+        }
+    }
+
+    /// return the (up to) two contiguous runs of free space
+    ///
+    /// the second run is empty unless the free region wraps around the end of
+    /// the backing allocation; it is always empty in non-circular mode.
+    pub fn space_segments(&mut self) -> (&mut [T], &mut [T]) {
+        let cap = self.capacity();
+        if !self.circular || cap == 0 {
+            return (&mut self.buf[self.end..], &mut []);
+        }
+        let free = self.available_space();
+        let e = self.end % cap;
+        if e + free <= cap {
+            (&mut self.buf[e..e + free], &mut [])
+        } else {
+            let first = cap - e;
+            let (head, tail) = self.buf.split_at_mut(e);
+            (&mut tail[..first], &mut head[..free - first])
+        }
+    }
+
     /// move remaining data to beginning of buffer and reset position() to 0
     pub fn shift(&mut self) {
+        if self.circular {
+            let cap = self.capacity();
+            if cap == 0 {
+                return;
+            }
+            let s = self.start % cap;
+            if s != 0 {
+                // rotate the whole allocation so the logical start lands at
+                // index 0, coalescing the (possibly wrapped) data into one run
+                self.buf.rotate_left(s);
+            }
+            let len = self.available_data();
+            self.start = 0;
+            self.end = len;
+            return;
+        }
+
         if self.start == 0 {
             return;
         }
@@ -130,7 +310,10 @@ impl Buffer {
     // need more tests
 
     /// delete `len` elements `start` elements from the read position
+    ///
+    /// unsupported in circular mode: this indexes the backing buffer linearly
     pub fn delete_slice(&mut self, start: usize, len: usize) -> Option<usize> {
+        debug_assert!(!self.circular, "delete_slice is not supported in circular mode");
         if start + len >= self.available_data() {
             return None;
         }
@@ -145,19 +328,22 @@ impl Buffer {
     }
 
     /// insert a slice at `start` elements from the read position
-    pub fn insert_slice(&mut self, data: &[u8], start: usize) -> Option<usize> {
+    ///
+    /// unsupported in circular mode: this indexes the backing buffer linearly
+    pub fn insert_slice(&mut self, data: &[T], start: usize) -> Option<usize> {
+        debug_assert!(!self.circular, "insert_slice is not supported in circular mode");
         if start >= self.available_data() {
             return None;
         }
-        if self.available_space() + data.len() > self.buf.capacity() {
+        if self.available_data() + data.len() > self.capacity() {
             // could not possibly fit new data
             return None;
-        } else if self.start + self.available_data() + data.len() > self.buf.capacity() {
+        } else if self.start + self.available_data() + data.len() > self.capacity() {
             // cannot fit new data as is, but can if we shift()
             // note: could reduce some copying by not shifting everything in all cases
             self.shift();
             // just in case
-            debug_assert!(self.start + self.available_data() + data.len() > self.buf.capacity());
+            debug_assert!(self.start + self.available_data() + data.len() <= self.capacity());
         }
 
         // copy elements after start position to end of new slice
@@ -175,7 +361,10 @@ impl Buffer {
     }
 
     /// replace range `start..start + len` with `data`
-    pub fn replace_slice(&mut self, mut data: &[u8], start: usize, len: usize) -> Option<usize> {
+    ///
+    /// unsupported in circular mode: this indexes the backing buffer linearly
+    pub fn replace_slice(&mut self, mut data: &[T], start: usize, len: usize) -> Option<usize> {
+        debug_assert!(!self.circular, "replace_slice is not supported in circular mode");
         match len.cmp(&data.len()) {
             std::cmp::Ordering::Greater => {
                 if self.start + start + len > self.end {
@@ -213,18 +402,29 @@ impl Buffer {
     }
 }
 
-impl Read for Buffer {
+impl Read for Buffer<u8> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        let read_from = self.data();
-        let read_len = read_from.len().min(buf.len());
-        buf[..read_len].copy_from_slice(&read_from[..read_len]);
-        self.start += read_len;
+        let read_len = {
+            let read_from = self.data();
+            let read_len = read_from.len().min(buf.len());
+            buf[..read_len].copy_from_slice(&read_from[..read_len]);
+            read_len
+        };
+        // route through consume() so circular mode gets the same wrap
+        // normalization instead of letting start/end grow unbounded
+        self.consume(read_len);
         Ok(read_len)
     }
 }
 
-impl Write for Buffer {
+impl Write for Buffer<u8> {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.grow_on_write {
+            // reserve fallibly so a growth failure surfaces as an I/O error
+            // rather than aborting the process
+            self.try_reserve(buf.len())
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::OutOfMemory, e))?;
+        }
         let write_to = self.space();
         let write_len = write_to.len().min(buf.len());
         write_to[..write_len].copy_from_slice(&buf[..write_len]);
@@ -238,6 +438,47 @@ impl Write for Buffer {
     }
 }
 
+// integration with the `bytes` crate so `Buffer` can stand in for `BytesMut`
+// as an I/O staging buffer (gated behind the `bytes` feature)
+#[cfg(feature = "bytes")]
+mod bytes_impl {
+    use super::Buffer;
+    use bytes::buf::UninitSlice;
+    use bytes::{Buf, BufMut};
+
+    impl Buf for Buffer<u8> {
+        fn remaining(&self) -> usize {
+            self.available_data()
+        }
+
+        fn chunk(&self) -> &[u8] {
+            // Buf::chunk is allowed to return a prefix, so hand back the leading
+            // contiguous run (the wrapped tail surfaces on the next chunk())
+            self.data_segments().0
+        }
+
+        fn advance(&mut self, cnt: usize) {
+            self.consume(cnt);
+        }
+    }
+
+    unsafe impl BufMut for Buffer<u8> {
+        fn remaining_mut(&self) -> usize {
+            self.available_space()
+        }
+
+        fn chunk_mut(&mut self) -> &mut UninitSlice {
+            // the backing vec is already zero-initialized, so handing the tail
+            // out as an `UninitSlice` only ever widens what the caller may do
+            UninitSlice::new(self.space())
+        }
+
+        unsafe fn advance_mut(&mut self, cnt: usize) {
+            self.fill(cnt);
+        }
+    }
+}
+
 // note: these tests are copied exactly from the original `circular` crate
 #[cfg(test)]
 mod tests {
@@ -328,6 +569,88 @@ mod tests {
         println!("{:?}", b.position());
     }
 
+    // the next four tests are not from the original crate: they cover the
+    // circular, generic and growth modes
+    #[test]
+    fn circular_wraparound() {
+        let mut b = Buffer::with_capacity_circular(8);
+        assert_eq!(b.available_space(), 8);
+        assert_eq!(b.write(&b"abcdef"[..]).ok(), Some(6));
+        assert_eq!(b.available_data(), 6);
+        assert_eq!(b.data(), &b"abcdef"[..]);
+
+        // advance the read head, freeing space at the front
+        b.consume(4);
+        assert_eq!(b.available_data(), 2);
+        assert_eq!(b.available_space(), 6);
+        // check non-destructively: a bare data() would rotate the ring here
+        assert_eq!(b.data_segments().0, &b"ef"[..]);
+
+        // fill past the end of the allocation; the free region is split in two
+        let (first, second) = b.space_segments();
+        assert_eq!(first.len(), 2);
+        assert_eq!(second.len(), 4);
+        first.copy_from_slice(&b"gh"[..]);
+        second.copy_from_slice(&b"ijkl"[..]);
+        b.fill(6);
+        assert_eq!(b.available_data(), 8);
+        assert_eq!(b.available_space(), 0);
+
+        // the live data now wraps around the end of the buffer
+        let (d1, d2) = b.data_segments();
+        assert_eq!(d1, &b"efgh"[..]);
+        assert_eq!(d2, &b"ijkl"[..]);
+
+        // shift() rotates it back into contiguous form
+        b.shift();
+        assert_eq!(b.data(), &b"efghijkl"[..]);
+    }
+
+    #[test]
+    fn circular_grow_preserves_wrapped_data() {
+        let mut b = Buffer::with_capacity_circular(8);
+        let s = b.space();
+        s[..6].copy_from_slice(&b"abcdef"[..]);
+        b.fill(6);
+        b.consume(4);
+
+        // fill across the wrap so the live data is split in two runs
+        let (first, second) = b.space_segments();
+        first.copy_from_slice(&b"gh"[..]);
+        second.copy_from_slice(&b"ijkl"[..]);
+        b.fill(6);
+        assert_eq!(b.data_segments(), (&b"efgh"[..], &b"ijkl"[..]));
+
+        // growing must not lose the wrapped tail
+        assert!(b.grow(16));
+        assert_eq!(b.available_data(), 8);
+        assert_eq!(b.data(), &b"efghijkl"[..]);
+    }
+
+    #[test]
+    fn generic_element_type() {
+        let mut b: Buffer<u32> = Buffer::with_capacity(4);
+        assert_eq!(b.available_space(), 4);
+        b.space()[..3].copy_from_slice(&[1, 2, 3]);
+        b.fill(3);
+        assert_eq!(b.data(), &[1u32, 2, 3][..]);
+
+        b.consume(1);
+        assert_eq!(b.data(), &[2u32, 3][..]);
+    }
+
+    #[test]
+    fn write_all_grows() {
+        let mut b = Buffer::with_growth(4);
+        assert_eq!(b.capacity(), 4);
+
+        // a frame larger than the current capacity must not be truncated
+        b.write_all(&b"abcdefghij"[..]).unwrap();
+        assert_eq!(b.available_data(), 10);
+        assert!(b.capacity() >= 10);
+        assert_eq!(b.data(), &b"abcdefghij"[..]);
+    }
+
     #[test]
     fn consume_without_shift() {
         let mut b = Buffer::with_capacity(10);
@@ -335,4 +658,26 @@ mod tests {
         b.consume_noshift(6);
         assert_eq!(b.position(), 6);
     }
+
+    // not from the original crate: covers the `bytes` integration
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn bytes_buf_roundtrip() {
+        use bytes::{Buf, BufMut};
+
+        let mut b = Buffer::with_capacity(8);
+        // remaining_mut and chunk_mut must agree on the writable length
+        assert_eq!(b.remaining_mut(), b.chunk_mut().len());
+
+        // BufMut: chunk_mut/advance_mut via put_slice
+        b.put_slice(b"hello");
+        assert_eq!(b.remaining(), 5);
+        assert_eq!(b.remaining_mut(), b.chunk_mut().len());
+
+        // Buf: chunk/advance
+        assert_eq!(b.chunk(), &b"hello"[..]);
+        b.advance(2);
+        assert_eq!(b.chunk(), &b"llo"[..]);
+        assert_eq!(b.remaining(), 3);
+    }
 }